@@ -1,13 +1,101 @@
 use std::collections::VecDeque;
+use std::io::Write;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use base64::prelude::*;
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use reqwest::{Client, Method, Response};
+use serde::Serialize;
 use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 
 use super::commons::Result;
 
+/// Backpressure depth of the channel a streaming request's serializer writes into; small enough
+/// that a slow network keeps the encoder's lead over the wire bounded.
+const STREAM_CHANNEL_CAPACITY: usize = 4;
+
+/// How long before its reported expiry we proactively refresh a cached OAuth2 token.
+const OAUTH2_REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+/// Request bodies larger than this (serialized, in bytes) are gzip-compressed when request
+/// compression is enabled.
+const REQUEST_COMPRESSION_THRESHOLD: usize = 16 * 1024;
+
+/// Status codes worth retrying: request timeout, rate limiting, and upstream/server hiccups.
+const RETRYABLE_STATUS_CODES: [u16; 6] = [408, 429, 500, 502, 503, 504];
+
+/// Whether (and how) `RetryPolicy::delay_for` randomizes the computed backoff.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RetryJitter {
+    /// Sleep for the full computed backoff every time; deterministic, good for tests.
+    None,
+    /// Sleep for a uniformly random duration in `[0, backoff]`. Spreads out retries from
+    /// clients that failed at the same time, avoiding a thundering herd on the server.
+    #[default]
+    Full,
+}
+
+/// Governs how `send_request` retries a failed call: how many attempts to make and how long to
+/// wait between them absent a server-provided `Retry-After`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: RetryJitter,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            jitter: RetryJitter::Full,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries: a single attempt, matching the client's historical single-shot behavior.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(0),
+            max_delay: Duration::from_millis(0),
+            jitter: RetryJitter::None,
+        }
+    }
+
+    /// Delay before the given retry attempt (1-indexed): `retry_after` capped at `max_delay` when
+    /// the server supplied one (no jitter applied), and otherwise `base_delay * 2^(attempt - 1)` capped at
+    /// `max_delay`, randomized per `self.jitter`.
+    fn delay_for(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+        let exponent = attempt.saturating_sub(1).min(16);
+        let backoff = self
+            .base_delay
+            .saturating_mul(1u32 << exponent)
+            .min(self.max_delay);
+        match self.jitter {
+            RetryJitter::None => backoff,
+            RetryJitter::Full => {
+                let jittered_ms =
+                    rand::Rng::gen_range(&mut rand::thread_rng(), 0..=backoff.as_millis() as u64);
+                Duration::from_millis(jittered_ms)
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum ChromaTokenHeader {
     Authorization,
@@ -25,6 +113,14 @@ pub enum ChromaAuthMethod {
         token: String,
         header: ChromaTokenHeader,
     },
+    /// OAuth2 client-credentials grant.  The access token is fetched lazily on first use and
+    /// cached until it approaches expiry, at which point it is transparently refreshed.
+    OAuth2 {
+        token_url: String,
+        client_id: String,
+        client_secret: String,
+        scopes: Vec<String>,
+    },
 }
 
 impl Default for ChromaAuthMethod {
@@ -33,6 +129,43 @@ impl Default for ChromaAuthMethod {
     }
 }
 
+/// A cached OAuth2 access token together with the instant at which it should be refreshed.
+#[derive(Clone, Debug)]
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+}
+
+/// A [`std::io::Write`] that forwards each write as a chunk over a channel, letting a blocking
+/// serializer feed a [`reqwest::Body`] stream without materializing the full output.
+struct ChannelWriter {
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+}
+
+impl std::io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::BrokenPipe, "stream consumer gone")
+            })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct OAuth2TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    #[allow(dead_code)]
+    #[serde(default)]
+    token_type: Option<String>,
+}
+
 #[derive(Default, Debug)]
 pub(super) struct APIClientAsync {
     client_pool: tokio::sync::Mutex<VecDeque<Arc<Client>>>,
@@ -44,6 +177,9 @@ pub(super) struct APIClientAsync {
     await_connection: tokio::sync::Notify,
     connections_alloc: AtomicUsize,
     connections_total: AtomicUsize,
+    oauth2_token: tokio::sync::Mutex<Option<CachedToken>>,
+    request_compression: bool,
+    retry_policy: RetryPolicy,
 }
 
 #[derive(serde::Deserialize)]
@@ -62,7 +198,7 @@ impl APIClientAsync {
         connections: usize,
     ) -> Self {
         let client_pool = (0..128)
-            .map(|_| Arc::new(Client::new()))
+            .map(|_| Arc::new(Self::build_pooled_client()))
             .collect::<VecDeque<_>>();
         let client_pool = tokio::sync::Mutex::new(client_pool);
         Self {
@@ -75,9 +211,37 @@ impl APIClientAsync {
             await_connection: tokio::sync::Notify::new(),
             connections_alloc: AtomicUsize::new(0),
             connections_total: AtomicUsize::new(connections),
+            oauth2_token: tokio::sync::Mutex::new(None),
+            request_compression: false,
+            retry_policy: RetryPolicy::default(),
         }
     }
 
+    /// Opt into gzip-compressing request bodies above [`REQUEST_COMPRESSION_THRESHOLD`], trading
+    /// CPU for transfer size on bandwidth-constrained links.
+    pub fn with_request_compression(mut self, enabled: bool) -> Self {
+        self.request_compression = enabled;
+        self
+    }
+
+    /// Override the retry policy applied to idempotent-safe requests (and opted-in POSTs).
+    /// Defaults to [`RetryPolicy::default`]; pass [`RetryPolicy::none`] to restore single-shot
+    /// behavior.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Build a pooled client with response decompression enabled; the server may gzip- or
+    /// brotli-encode its responses regardless of whether request compression is turned on.
+    fn build_pooled_client() -> Client {
+        Client::builder()
+            .gzip(true)
+            .brotli(true)
+            .build()
+            .expect("failed to build reqwest client")
+    }
+
     fn database_url(&self, path: &str) -> String {
         assert!(path.starts_with('/'));
         format!(
@@ -89,32 +253,60 @@ impl APIClientAsync {
     /// GET from a database-scoped path.
     pub async fn get_database(&self, path: &str) -> Result<Response> {
         let url = self.database_url(path);
-        self.send_request(Method::GET, &url, None).await
+        self.send_request(Method::GET, &url, None, false).await
     }
 
-    /// POST to a database-scoped path.
+    /// POST to a database-scoped path.  Not retried: POST bodies may not be idempotent-safe to
+    /// replay.  Use [`Self::post_database_retryable`] when the caller knows it's safe to retry.
     pub async fn post_database(&self, path: &str, json_body: Option<Value>) -> Result<Response> {
         let url = self.database_url(path);
-        self.send_request(Method::POST, &url, json_body).await
+        self.send_request(Method::POST, &url, json_body, false)
+            .await
+    }
+
+    /// POST to a database-scoped path, opting the request into the client's retry policy.  Only
+    /// use this for POSTs the caller knows are safe to replay (e.g. idempotent upserts keyed by
+    /// id).
+    pub async fn post_database_retryable(
+        &self,
+        path: &str,
+        json_body: Option<Value>,
+    ) -> Result<Response> {
+        let url = self.database_url(path);
+        self.send_request(Method::POST, &url, json_body, true).await
+    }
+
+    /// POST to a database-scoped path, serializing `json_body` straight into the outgoing body
+    /// stream instead of building a `serde_json::Value` first. Use this for large upserts (ids,
+    /// documents, embedding vectors) where materializing the whole payload would otherwise force
+    /// it into memory twice. Not retried: the body is consumed as it streams, so a failed send
+    /// can't be replayed.
+    pub async fn post_database_streaming<T>(&self, path: &str, json_body: T) -> Result<Response>
+    where
+        T: Serialize + Send + 'static,
+    {
+        let url = self.database_url(path);
+        self.send_request_streaming(Method::POST, &url, json_body)
+            .await
     }
 
     /// PUT to a database-scoped path.
     pub async fn put_database(&self, path: &str, json_body: Option<Value>) -> Result<Response> {
         let url = self.database_url(path);
-        self.send_request(Method::PUT, &url, json_body).await
+        self.send_request(Method::PUT, &url, json_body, false).await
     }
 
     /// DELETE to a database-scoped path.  This does not delete a database.
     pub async fn delete_database(&self, path: &str) -> Result<Response> {
         let url = self.database_url(path);
-        self.send_request(Method::DELETE, &url, None).await
+        self.send_request(Method::DELETE, &url, None, false).await
     }
 
     /// GET from a v1-scoped path.
     pub async fn get_v1(&self, path: &str) -> Result<Response> {
         assert!(path.starts_with('/'));
         let url = format!("{}{}", self.api_endpoint_v1, path);
-        self.send_request(Method::GET, &url, None).await
+        self.send_request(Method::GET, &url, None, false).await
     }
 
     /// Hit the auth endpoint to resolve tenant and database prior to instantiating a client.
@@ -122,54 +314,253 @@ impl APIClientAsync {
         let url = format!("{}/api/v2/auth/identity", url);
         let client = Client::new();
         let request = client.request(Method::GET, url);
-        let resp = Self::send_request_no_self(request, auth, None).await?;
+        let resp = Self::send_request_no_self(request, auth, None, None, false).await?;
         let user_identity: UserIdentity = resp.json().await?;
         Ok(user_identity)
     }
 
+    /// Send a request, retrying it per `self.retry_policy` when it is idempotent-safe (GET/PUT
+    /// /DELETE always, POST only when `retry_post` is set) and fails with a retryable error.
     async fn send_request(
         &self,
         method: Method,
         url: &str,
         json_body: Option<Value>,
+        retry_post: bool,
     ) -> Result<Response> {
-        let client = {
-            loop {
-                let mut pool = self.client_pool.lock().await;
-                if let Some(client) = pool.pop_front() {
-                    break client;
-                }
-                let alloc = self.connections_alloc.load(Ordering::Relaxed);
-                // If we haven't allocated everything, and we successfully allocated one more,
-                // break with a new client.
-                if alloc < self.connections_total.load(Ordering::Relaxed)
-                    && self
-                        .connections_alloc
-                        .compare_exchange(alloc, alloc + 1, Ordering::Relaxed, Ordering::Relaxed)
-                        .is_ok()
-                {
-                    break Arc::new(Client::new());
-                }
-                drop(pool);
-                self.await_connection.notified().await;
+        let retryable_method = matches!(
+            method,
+            Method::GET | Method::PUT | Method::DELETE | Method::HEAD | Method::OPTIONS
+        ) || (method == Method::POST && retry_post);
+
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .send_request_once(method.clone(), url, json_body.clone())
+                .await;
+            let err = match result {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            if !retryable_method {
+                return Err(err);
             }
-        };
+            let Some(retry_after) = Self::retryable_delay_hint(&err) else {
+                return Err(err);
+            };
+            attempt += 1;
+            if attempt >= self.retry_policy.max_attempts {
+                return Err(err);
+            }
+            let delay = self.retry_policy.delay_for(attempt, retry_after);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// If `err` represents a retryable failure (a connection error, or one of
+    /// [`RETRYABLE_STATUS_CODES`]), return the server-suggested delay, if any.
+    fn retryable_delay_hint(err: &anyhow::Error) -> Option<Option<Duration>> {
+        if let Some(api_err) = err.downcast_ref::<ChromaApiError>() {
+            return RETRYABLE_STATUS_CODES
+                .contains(&api_err.status_code())
+                .then(|| api_err.retry_after());
+        }
+        if let Some(transport_err) = err.downcast_ref::<reqwest::Error>() {
+            if transport_err.is_connect() || transport_err.is_timeout() {
+                return Some(None);
+            }
+        }
+        None
+    }
+
+    /// A single attempt at sending `method url` with `json_body`, checking a client out of the
+    /// pool and returning it exactly once regardless of the outcome.
+    async fn send_request_once(
+        &self,
+        method: Method,
+        url: &str,
+        json_body: Option<Value>,
+    ) -> Result<Response> {
+        let client = self.checkout_client().await;
         let request = client.request(method, url);
-        let res = Self::send_request_no_self(request, &self.auth_method, json_body).await;
-        {
+        let res = Self::send_request_no_self(
+            request,
+            &self.auth_method,
+            json_body,
+            Some(&self.oauth2_token),
+            self.request_compression,
+        )
+        .await;
+        self.checkin_client(client).await;
+        res
+    }
+
+    /// Send `json_body` as a streamed request body: a blocking task serializes it (optionally
+    /// gzip-encoding as it goes) chunk by chunk into a channel that backs the outgoing
+    /// `reqwest::Body`, so the full payload is never held in memory as a single buffer. Unlike
+    /// `send_request_no_self`, compression (when enabled) always applies here regardless of
+    /// `REQUEST_COMPRESSION_THRESHOLD`: streaming exists precisely to avoid measuring the whole
+    /// serialized size up front.
+    async fn send_request_streaming<T>(
+        &self,
+        method: Method,
+        url: &str,
+        json_body: T,
+    ) -> Result<Response>
+    where
+        T: Serialize + Send + 'static,
+    {
+        let client = self.checkout_client().await;
+        let compress = self.request_compression;
+
+        let mut request = client
+            .request(method, url)
+            .header("Content-Type", "application/json");
+        if compress {
+            request = request.header("Content-Encoding", "gzip");
+        }
+        // Resolve auth before we start encoding: an OAuth2 refresh failure should fail fast
+        // rather than leave a blocking-pool thread stuck writing into an undrained channel.
+        let request =
+            match Self::apply_auth(request, &self.auth_method, Some(&self.oauth2_token)).await {
+                Ok(request) => request,
+                Err(err) => {
+                    self.checkin_client(client).await;
+                    return Err(err);
+                }
+            };
+
+        let (tx, rx) = mpsc::channel::<std::io::Result<Bytes>>(STREAM_CHANNEL_CAPACITY);
+        let encode_task = tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+            let writer = ChannelWriter { tx };
+            if compress {
+                let mut encoder = GzEncoder::new(writer, Compression::default());
+                serde_json::to_writer(&mut encoder, &json_body)?;
+                encoder.finish()?;
+            } else {
+                let mut writer = writer;
+                serde_json::to_writer(&mut writer, &json_body)?;
+            }
+            Ok(())
+        });
+        let request = request.body(reqwest::Body::wrap_stream(ReceiverStream::new(rx)));
+        let res = Self::finish_request(request).await;
+        let encode_result = encode_task.await;
+        self.checkin_client(client).await;
+
+        // The HTTP-level outcome takes priority; a broken-pipe write error from the encoder is
+        // usually just a symptom of the request having already failed for some other reason.
+        match res {
+            Err(err) => Err(err),
+            Ok(response) => match encode_result {
+                Ok(Ok(())) => Ok(response),
+                Ok(Err(io_err)) => Err(io_err.into()),
+                Err(join_err) => anyhow::bail!("streaming encode task panicked: {join_err}"),
+            },
+        }
+    }
+
+    /// Check a client out of the pool, allocating a fresh one if under `connections_total` and
+    /// the pool is empty, otherwise waiting for one to be returned.
+    async fn checkout_client(&self) -> Arc<Client> {
+        loop {
             let mut pool = self.client_pool.lock().await;
-            pool.push_front(client);
-            self.await_connection.notify_one();
+            if let Some(client) = pool.pop_front() {
+                return client;
+            }
+            let alloc = self.connections_alloc.load(Ordering::Relaxed);
+            // If we haven't allocated everything, and we successfully allocated one more,
+            // return a new client.
+            if alloc < self.connections_total.load(Ordering::Relaxed)
+                && self
+                    .connections_alloc
+                    .compare_exchange(alloc, alloc + 1, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return Arc::new(Self::build_pooled_client());
+            }
+            drop(pool);
+            self.await_connection.notified().await;
         }
-        res
     }
 
-    async fn send_request_no_self(
+    /// Return a client to the pool and wake one waiter, mirroring `checkout_client` exactly once
+    /// per checkout regardless of how the call in between turned out.
+    async fn checkin_client(&self, client: Arc<Client>) {
+        let mut pool = self.client_pool.lock().await;
+        pool.push_front(client);
+        self.await_connection.notify_one();
+    }
+
+    /// Return a valid bearer token for the OAuth2 client-credentials grant, refreshing it if
+    /// absent or within `OAUTH2_REFRESH_MARGIN` of expiry.  When `cache` is `None` (the
+    /// pre-client `get_auth` bootstrap call) a fresh token is fetched on every call.
+    async fn oauth2_access_token(
+        cache: Option<&tokio::sync::Mutex<Option<CachedToken>>>,
+        token_url: &str,
+        client_id: &str,
+        client_secret: &str,
+        scopes: &[String],
+    ) -> Result<String> {
+        let Some(cache) = cache else {
+            let token =
+                Self::fetch_oauth2_token(token_url, client_id, client_secret, scopes).await?;
+            return Ok(token.access_token);
+        };
+        // Holding the lock across the (potential) refresh request coalesces concurrent callers
+        // onto a single token fetch instead of stampeding the token endpoint.
+        let mut guard = cache.lock().await;
+        if let Some(cached) = guard.as_ref() {
+            if cached.refresh_at > Instant::now() {
+                return Ok(cached.access_token.clone());
+            }
+        }
+        let token = Self::fetch_oauth2_token(token_url, client_id, client_secret, scopes).await?;
+        let access_token = token.access_token.clone();
+        *guard = Some(token);
+        Ok(access_token)
+    }
+
+    async fn fetch_oauth2_token(
+        token_url: &str,
+        client_id: &str,
+        client_secret: &str,
+        scopes: &[String],
+    ) -> Result<CachedToken> {
+        let client = Client::new();
+        let mut form = vec![("grant_type".to_string(), "client_credentials".to_string())];
+        if !scopes.is_empty() {
+            form.push(("scope".to_string(), scopes.join(" ")));
+        }
+        let response = client
+            .post(token_url)
+            .basic_auth(client_id, Some(client_secret))
+            .form(&form)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = Self::parse_retry_after(response.headers());
+            let error_text = response.text().await?;
+            return Err(
+                ChromaApiError::from_oauth2_response(status, retry_after, &error_text).into(),
+            );
+        }
+        let token: OAuth2TokenResponse = response.json().await?;
+        let refresh_at = Instant::now()
+            + Duration::from_secs(token.expires_in).saturating_sub(OAUTH2_REFRESH_MARGIN);
+        Ok(CachedToken {
+            access_token: token.access_token,
+            refresh_at,
+        })
+    }
+
+    /// Attach whatever headers `auth_method` requires, refreshing an OAuth2 token if needed.
+    async fn apply_auth(
         mut request: reqwest::RequestBuilder,
         auth_method: &ChromaAuthMethod,
-        json_body: Option<Value>,
-    ) -> Result<Response> {
-        // Add auth headers if needed
+        oauth2_cache: Option<&tokio::sync::Mutex<Option<CachedToken>>>,
+    ) -> Result<reqwest::RequestBuilder> {
         match &auth_method {
             ChromaAuthMethod::None => {}
             ChromaAuthMethod::BasicAuth { username, password } => {
@@ -184,28 +575,443 @@ impl APIClientAsync {
                     request = request.header("X-Chroma-Token", token);
                 }
             },
+            ChromaAuthMethod::OAuth2 {
+                token_url,
+                client_id,
+                client_secret,
+                scopes,
+            } => {
+                let access_token = Self::oauth2_access_token(
+                    oauth2_cache,
+                    token_url,
+                    client_id,
+                    client_secret,
+                    scopes,
+                )
+                .await?;
+                request = request.header("Authorization", format!("Bearer {access_token}"));
+            }
         }
+        Ok(request)
+    }
 
-        // Add JSON body if present
+    async fn send_request_no_self(
+        request: reqwest::RequestBuilder,
+        auth_method: &ChromaAuthMethod,
+        json_body: Option<Value>,
+        oauth2_cache: Option<&tokio::sync::Mutex<Option<CachedToken>>>,
+        compress: bool,
+    ) -> Result<Response> {
+        let mut request = Self::apply_auth(request, auth_method, oauth2_cache).await?;
+
+        // Add JSON body if present, compressing it when it is large enough to be worth the CPU.
         if let Some(body) = json_body {
-            request = request
-                .header("Content-Type", "application/json")
-                .json(&body);
+            let serialized = serde_json::to_vec(&body)?;
+            if compress && serialized.len() > REQUEST_COMPRESSION_THRESHOLD {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&serialized)?;
+                let compressed = encoder.finish()?;
+                request = request
+                    .header("Content-Type", "application/json")
+                    .header("Content-Encoding", "gzip")
+                    .body(compressed);
+            } else {
+                request = request
+                    .header("Content-Type", "application/json")
+                    .body(serialized);
+            }
         }
 
+        Self::finish_request(request).await
+    }
+
+    /// Send a fully-built request and map a non-2xx response into a [`ChromaApiError`].
+    async fn finish_request(request: reqwest::RequestBuilder) -> Result<Response> {
         let response = request.send().await?;
         let status = response.status();
 
         if status.is_success() {
             Ok(response)
         } else {
+            let retry_after = Self::parse_retry_after(response.headers());
             let error_text = response.text().await?;
-            anyhow::bail!(
-                "{} {}: {}",
-                status.as_u16(),
-                status.canonical_reason().unwrap_or("Unknown"),
-                error_text
-            )
+            Err(ChromaApiError::from_response(status, retry_after, &error_text).into())
+        }
+    }
+
+    /// Parse a `Retry-After` header in either delta-seconds or HTTP-date form.
+    fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+        let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
         }
+        let target = httpdate::parse_http_date(value).ok()?;
+        target.duration_since(std::time::SystemTime::now()).ok()
+    }
+}
+
+/// A JSON error body Chroma returns alongside a non-2xx status, e.g.
+/// `{ "error": "NotFoundError", "message": "Collection foo not found" }`.
+#[derive(serde::Deserialize, Default)]
+struct ChromaErrorBody {
+    error: Option<String>,
+    message: Option<String>,
+}
+
+/// A JSON error body returned by an OAuth2 token endpoint on a non-2xx response, per the
+/// client-credentials error format in [RFC 6749 §5.2](https://www.rfc-editor.org/rfc/rfc6749#section-5.2),
+/// e.g. `{ "error": "invalid_client", "error_description": "Client authentication failed" }`.
+/// This is a different schema from [`ChromaErrorBody`]: the human-readable text lives in
+/// `error_description`, not `message`.
+#[derive(serde::Deserialize, Default)]
+struct OAuth2ErrorBody {
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Structured errors mapped from the HTTP status (and, where present, the JSON error body) of a
+/// non-2xx Chroma response, so callers can branch on the failure kind instead of scraping text.
+#[derive(Debug)]
+pub enum ChromaApiError {
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    RateLimited {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    /// Not yet produced by [`Self::from_response`]: Chroma does not document a dedicated
+    /// quota-exceeded status code, so a 402 currently falls into `Other` rather than risk
+    /// guessing wrong. Kept as a variant so callers can match on it once a real status code is
+    /// confirmed against the server.
+    #[allow(dead_code)]
+    QuotaExceeded(String),
+    Server(u16, String),
+    Other {
+        status: u16,
+        message: String,
+    },
+}
+
+impl ChromaApiError {
+    fn from_response(
+        status: reqwest::StatusCode,
+        retry_after: Option<Duration>,
+        body: &str,
+    ) -> Self {
+        let parsed: ChromaErrorBody = serde_json::from_str(body).unwrap_or_default();
+        let message = parsed
+            .message
+            .or(parsed.error)
+            .unwrap_or_else(|| body.to_string());
+        match status.as_u16() {
+            401 => ChromaApiError::Unauthorized(message),
+            403 => ChromaApiError::Forbidden(message),
+            404 => ChromaApiError::NotFound(message),
+            409 => ChromaApiError::Conflict(message),
+            429 => ChromaApiError::RateLimited {
+                retry_after,
+                message,
+            },
+            // Chroma does not document a dedicated quota-exceeded status; 402 and anything else
+            // unrecognized fall through to `Other` until that's confirmed against the server.
+            500..=599 => ChromaApiError::Server(status.as_u16(), message),
+            other => ChromaApiError::Other {
+                status: other,
+                message,
+            },
+        }
+    }
+
+    /// Like [`Self::from_response`], but parses the body as an OAuth2 token-endpoint error
+    /// ([`OAuth2ErrorBody`]) rather than Chroma's own error schema, so `error_description` isn't
+    /// lost behind a field name Chroma's API happens not to use. Only 401, 403, 429 and 5xx map to
+    /// their dedicated variants; every other status (404 and 409 included) becomes `Other`.
+    fn from_oauth2_response(
+        status: reqwest::StatusCode,
+        retry_after: Option<Duration>,
+        body: &str,
+    ) -> Self {
+        let parsed: OAuth2ErrorBody = serde_json::from_str(body).unwrap_or_default();
+        let message = parsed
+            .error_description
+            .or(parsed.error)
+            .unwrap_or_else(|| body.to_string());
+        match status.as_u16() {
+            401 => ChromaApiError::Unauthorized(message),
+            403 => ChromaApiError::Forbidden(message),
+            429 => ChromaApiError::RateLimited {
+                retry_after,
+                message,
+            },
+            500..=599 => ChromaApiError::Server(status.as_u16(), message),
+            other => ChromaApiError::Other {
+                status: other,
+                message,
+            },
+        }
+    }
+
+    /// The HTTP status code this error was mapped from.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ChromaApiError::Unauthorized(_) => 401,
+            ChromaApiError::Forbidden(_) => 403,
+            ChromaApiError::NotFound(_) => 404,
+            ChromaApiError::Conflict(_) => 409,
+            ChromaApiError::RateLimited { .. } => 429,
+            ChromaApiError::QuotaExceeded(_) => 402,
+            ChromaApiError::Server(status, _) => *status,
+            ChromaApiError::Other { status, .. } => *status,
+        }
+    }
+
+    /// The server-suggested `Retry-After` delay, if the error carried one.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ChromaApiError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ChromaApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChromaApiError::Unauthorized(message) => write!(f, "401 Unauthorized: {message}"),
+            ChromaApiError::Forbidden(message) => write!(f, "403 Forbidden: {message}"),
+            ChromaApiError::NotFound(message) => write!(f, "404 Not Found: {message}"),
+            ChromaApiError::Conflict(message) => write!(f, "409 Conflict: {message}"),
+            ChromaApiError::RateLimited {
+                retry_after,
+                message,
+            } => match retry_after {
+                Some(retry_after) => write!(
+                    f,
+                    "429 Too Many Requests (retry after {retry_after:?}): {message}"
+                ),
+                None => write!(f, "429 Too Many Requests: {message}"),
+            },
+            ChromaApiError::QuotaExceeded(message) => write!(f, "402 Quota Exceeded: {message}"),
+            ChromaApiError::Server(status, message) => {
+                write!(f, "{status} Server Error: {message}")
+            }
+            ChromaApiError::Other { status, message } => write!(f, "{status}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ChromaApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_response_maps_known_status_codes() {
+        let cases = [
+            (reqwest::StatusCode::UNAUTHORIZED, 401),
+            (reqwest::StatusCode::FORBIDDEN, 403),
+            (reqwest::StatusCode::NOT_FOUND, 404),
+            (reqwest::StatusCode::CONFLICT, 409),
+            (reqwest::StatusCode::TOO_MANY_REQUESTS, 429),
+            (reqwest::StatusCode::INTERNAL_SERVER_ERROR, 500),
+            (reqwest::StatusCode::BAD_GATEWAY, 502),
+        ];
+        for (status, expected) in cases {
+            let err = ChromaApiError::from_response(status, None, "{}");
+            assert_eq!(err.status_code(), expected);
+        }
+    }
+
+    #[test]
+    fn from_response_prefers_json_message_over_error_field() {
+        let body = r#"{"error": "NotFoundError", "message": "collection foo not found"}"#;
+        let err = ChromaApiError::from_response(reqwest::StatusCode::NOT_FOUND, None, body);
+        assert_eq!(err.to_string(), "404 Not Found: collection foo not found");
+    }
+
+    #[test]
+    fn from_response_falls_back_to_error_field_without_message() {
+        let body = r#"{"error": "NotFoundError"}"#;
+        let err = ChromaApiError::from_response(reqwest::StatusCode::NOT_FOUND, None, body);
+        assert_eq!(err.to_string(), "404 Not Found: NotFoundError");
+    }
+
+    #[test]
+    fn from_response_falls_back_to_raw_body_when_not_json() {
+        let err =
+            ChromaApiError::from_response(reqwest::StatusCode::BAD_GATEWAY, None, "upstream down");
+        assert_eq!(err.to_string(), "502 Server Error: upstream down");
+    }
+
+    #[test]
+    fn from_response_carries_retry_after_into_rate_limited() {
+        let retry_after = Some(Duration::from_secs(5));
+        let err = ChromaApiError::from_response(
+            reqwest::StatusCode::TOO_MANY_REQUESTS,
+            retry_after,
+            "{}",
+        );
+        assert_eq!(err.retry_after(), retry_after);
+    }
+
+    #[test]
+    fn from_response_unrecognized_status_falls_through_to_other() {
+        let err = ChromaApiError::from_response(reqwest::StatusCode::PAYMENT_REQUIRED, None, "{}");
+        assert!(matches!(err, ChromaApiError::Other { status: 402, .. }));
+    }
+
+    #[test]
+    fn from_oauth2_response_prefers_error_description_over_error_field() {
+        let body =
+            r#"{"error": "invalid_client", "error_description": "Client authentication failed"}"#;
+        let err =
+            ChromaApiError::from_oauth2_response(reqwest::StatusCode::UNAUTHORIZED, None, body);
+        assert_eq!(
+            err.to_string(),
+            "401 Unauthorized: Client authentication failed"
+        );
+    }
+
+    #[test]
+    fn from_oauth2_response_falls_back_to_error_field_without_description() {
+        let body = r#"{"error": "invalid_client"}"#;
+        let err =
+            ChromaApiError::from_oauth2_response(reqwest::StatusCode::UNAUTHORIZED, None, body);
+        assert_eq!(err.to_string(), "401 Unauthorized: invalid_client");
+    }
+
+    #[test]
+    fn from_oauth2_response_falls_back_to_raw_body_when_not_json() {
+        let err = ChromaApiError::from_oauth2_response(
+            reqwest::StatusCode::BAD_GATEWAY,
+            None,
+            "upstream down",
+        );
+        assert_eq!(err.to_string(), "502 Server Error: upstream down");
+    }
+
+    fn header_map(retry_after: &str) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            reqwest::header::HeaderValue::from_str(retry_after).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn parse_retry_after_reads_delta_seconds() {
+        let headers = header_map("120");
+        assert_eq!(
+            APIClientAsync::parse_retry_after(&headers),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_reads_http_date() {
+        // An arbitrary HTTP-date far enough in the future that `duration_since(now)` is positive
+        // for as long as this test remains in the suite.
+        let headers = header_map("Fri, 01 Jan 2100 00:00:00 GMT");
+        assert!(APIClientAsync::parse_retry_after(&headers).is_some());
+    }
+
+    #[test]
+    fn parse_retry_after_rejects_garbage() {
+        let headers = header_map("not-a-date-or-number");
+        assert_eq!(APIClientAsync::parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn parse_retry_after_missing_header_is_none() {
+        let headers = reqwest::header::HeaderMap::new();
+        assert_eq!(APIClientAsync::parse_retry_after(&headers), None);
+    }
+
+    fn policy(jitter: RetryJitter) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn delay_for_without_jitter_grows_exponentially() {
+        let policy = policy(RetryJitter::None);
+        assert_eq!(policy.delay_for(1, None), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(2, None), Duration::from_millis(200));
+        assert_eq!(policy.delay_for(3, None), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn delay_for_without_jitter_caps_at_max_delay() {
+        let policy = policy(RetryJitter::None);
+        assert_eq!(policy.delay_for(20, None), policy.max_delay);
+    }
+
+    #[test]
+    fn delay_for_with_full_jitter_stays_within_backoff() {
+        let policy = policy(RetryJitter::Full);
+        for attempt in 1..=6 {
+            let backoff = policy
+                .base_delay
+                .saturating_mul(1u32 << (attempt - 1))
+                .min(policy.max_delay);
+            let delay = policy.delay_for(attempt, None);
+            assert!(
+                delay <= backoff,
+                "attempt {attempt}: {delay:?} > {backoff:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn delay_for_prefers_retry_after_over_backoff() {
+        for jitter in [RetryJitter::None, RetryJitter::Full] {
+            let policy = policy(jitter);
+            let retry_after = Duration::from_secs(1);
+            assert_eq!(policy.delay_for(1, Some(retry_after)), retry_after);
+        }
+    }
+
+    #[test]
+    fn delay_for_caps_retry_after_at_max_delay() {
+        let policy = policy(RetryJitter::None);
+        let retry_after = Duration::from_secs(60);
+        assert_eq!(policy.delay_for(1, Some(retry_after)), policy.max_delay);
+    }
+
+    #[test]
+    fn retryable_delay_hint_retries_server_errors_and_rate_limits() {
+        let server_err: anyhow::Error =
+            ChromaApiError::from_response(reqwest::StatusCode::BAD_GATEWAY, None, "{}").into();
+        assert_eq!(
+            APIClientAsync::retryable_delay_hint(&server_err),
+            Some(None)
+        );
+
+        let retry_after = Some(Duration::from_secs(3));
+        let rate_limited: anyhow::Error = ChromaApiError::from_response(
+            reqwest::StatusCode::TOO_MANY_REQUESTS,
+            retry_after,
+            "{}",
+        )
+        .into();
+        assert_eq!(
+            APIClientAsync::retryable_delay_hint(&rate_limited),
+            Some(retry_after)
+        );
+    }
+
+    #[test]
+    fn retryable_delay_hint_does_not_retry_client_errors() {
+        let not_found: anyhow::Error =
+            ChromaApiError::from_response(reqwest::StatusCode::NOT_FOUND, None, "{}").into();
+        assert_eq!(APIClientAsync::retryable_delay_hint(&not_found), None);
     }
 }